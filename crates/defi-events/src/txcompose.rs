@@ -17,15 +17,64 @@ use defi_types::{GethStateUpdateVec, Opcodes};
 
 use crate::Message;
 
+pub const ELASTICITY_MULTIPLIER: u128 = 2;
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u128 = 8;
+
+pub fn predict_base_fee(parent_gas_used: u128, parent_gas_limit: u128, parent_base_fee: U256) -> U256 {
+    let gas_target = parent_gas_limit / ELASTICITY_MULTIPLIER;
+
+    if gas_target == 0 {
+        return parent_base_fee;
+    }
+
+    match parent_gas_used.cmp(&gas_target) {
+        std::cmp::Ordering::Equal => parent_base_fee,
+        std::cmp::Ordering::Greater => {
+            let gas_used_delta = U256::from(parent_gas_used - gas_target);
+            let base_fee_delta = (parent_base_fee * gas_used_delta / U256::from(gas_target) / U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR)).max(U256::from(1));
+            parent_base_fee + base_fee_delta
+        }
+        std::cmp::Ordering::Less => {
+            let gas_used_delta = U256::from(gas_target - parent_gas_used);
+            let base_fee_delta = parent_base_fee * gas_used_delta / U256::from(gas_target) / U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR);
+            parent_base_fee.saturating_sub(base_fee_delta)
+        }
+    }
+}
+
+pub fn forecast_base_fees(parent_base_fee: U256, parent_gas_used: u128, parent_gas_limit: u128, n_blocks: u64) -> Vec<U256> {
+    let mut base_fee = parent_base_fee;
+    let mut forecast = Vec::with_capacity(n_blocks as usize);
+
+    for _ in 0..n_blocks {
+        base_fee = predict_base_fee(parent_gas_used, parent_gas_limit, base_fee);
+        forecast.push(base_fee);
+    }
+
+    forecast
+}
+
 #[derive(Clone, Debug)]
 pub enum TxState {
     Stuffing(Transaction),
     SignatureRequired(TransactionRequest),
+    SignatureRequired1559 {
+        request: TransactionRequest,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    },
     ReadyForBroadcast(Bytes),
     ReadyForBroadcastStuffing(Bytes),
 }
 
 impl TxState {
+    pub fn signature_required_1559(mut request: TransactionRequest, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> Self {
+        request.max_fee_per_gas = Some(max_fee_per_gas);
+        request.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+        request.gas_price = None;
+        TxState::SignatureRequired1559 { request, max_fee_per_gas, max_priority_fee_per_gas }
+    }
+
     pub fn rlp(&self) -> Result<Bytes> {
         match self {
             TxState::Stuffing(t) => {
@@ -220,6 +269,10 @@ pub struct TxComposeData {
     pub stuffing_txs: Vec<Transaction>,
     pub block: BlockNumber,
     pub block_timestamp: u64,
+    pub block_horizon: u64,
+    pub parent_gas_used: u128,
+    pub parent_gas_limit: u128,
+    pub parent_base_fee: U256,
     pub swap: SwapType,
     pub opcodes: Option<Opcodes>,
     pub tx_bundle: Option<Vec<TxState>>,
@@ -272,6 +325,27 @@ impl TxComposeData {
             self.swap.abs_profit_eth() / U256::from(self.gas)
         }
     }
+
+    pub fn next_block_base_fee(&self) -> U256 {
+        predict_base_fee(self.parent_gas_used, self.parent_gas_limit, self.parent_base_fee)
+    }
+
+    pub fn forecasted_base_fees(&self) -> Vec<U256> {
+        forecast_base_fees(self.parent_base_fee, self.parent_gas_used, self.parent_gas_limit, self.block_horizon.max(1))
+    }
+
+    pub fn worst_case_base_fee(&self) -> U256 {
+        self.forecasted_base_fees().into_iter().max().unwrap_or(self.parent_base_fee)
+    }
+
+    pub fn update_gas_fee(&mut self) {
+        self.gas_fee = (self.worst_case_base_fee() + U256::from(self.priority_gas_fee)).to::<u128>();
+    }
+
+    pub fn net_profit_eth(&self) -> U256 {
+        let burned = self.next_block_base_fee() * U256::from(self.gas);
+        self.swap.abs_profit_eth().saturating_sub(burned)
+    }
 }
 
 impl Default for TxComposeData {
@@ -288,6 +362,10 @@ impl Default for TxComposeData {
             stuffing_txs: Vec::new(),
             block: Default::default(),
             block_timestamp: Default::default(),
+            block_horizon: 1,
+            parent_gas_used: Default::default(),
+            parent_gas_limit: Default::default(),
+            parent_base_fee: Default::default(),
             swap: SwapType::None,
             opcodes: None,
             tx_bundle: None,
@@ -307,6 +385,7 @@ pub struct TxComposeBest {
     validity_pct: Option<U256>,
     best_profit_swap: Option<TxComposeData>,
     best_profit_gas_ratio_swap: Option<TxComposeData>,
+    best_net_profit_swap: Option<TxComposeData>,
     best_tips_swap: Option<TxComposeData>,
     best_tips_gas_ratio_swap: Option<TxComposeData>,
 
@@ -318,6 +397,7 @@ impl Default for TxComposeBest {
             validity_pct: None,
             best_profit_swap: None,
             best_profit_gas_ratio_swap: None,
+            best_net_profit_swap: None,
             best_tips_swap: None,
             best_tips_gas_ratio_swap: None,
         }
@@ -357,6 +437,28 @@ impl TxComposeBest {
             }
         }
 
+        match &self.best_net_profit_swap {
+            None => {
+                self.best_net_profit_swap = Some(request.clone());
+                is_ok = true;
+            }
+            Some(best_swap) => {
+                if best_swap.net_profit_eth() < request.net_profit_eth() {
+                    self.best_net_profit_swap = Some(request.clone());
+                    is_ok = true;
+                } else {
+                    match self.validity_pct {
+                        Some(pct) => {
+                            if (best_swap.net_profit_eth() * pct) / U256::from(10000) < request.net_profit_eth() {
+                                is_ok = true
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+
         if request.tips.is_some() {
             match &self.best_tips_swap {
                 Some(best_swap) => {
@@ -435,7 +537,8 @@ impl TxComposeBest {
 pub type MessageTxCompose = Message<TxCompose>;
 
 impl MessageTxCompose {
-    pub fn encode(data: TxComposeData) -> Self {
+    pub fn encode(mut data: TxComposeData) -> Self {
+        data.update_gas_fee();
         Message::new(TxCompose::Encode(data))
     }
 
@@ -443,7 +546,8 @@ impl MessageTxCompose {
         Message::new(TxCompose::Sign(data))
     }
 
-    pub fn estimate(data: TxComposeData) -> Self {
+    pub fn estimate(mut data: TxComposeData) -> Self {
+        data.update_gas_fee();
         Message::new(TxCompose::Estimate(data))
     }
 
@@ -470,4 +574,67 @@ mod test {
             println!("{c:?}");
         }
     }
+
+    #[test]
+    fn test_predict_base_fee_at_target() {
+        assert_eq!(predict_base_fee(10_000_000, 20_000_000, U256::from(100u64)), U256::from(100u64));
+    }
+
+    #[test]
+    fn test_predict_base_fee_above_target() {
+        // gas_target = 10, delta = 5, bump = 100 * 5 / 10 / 8 = 6
+        assert_eq!(predict_base_fee(15, 20, U256::from(100u64)), U256::from(106u64));
+    }
+
+    #[test]
+    fn test_predict_base_fee_above_target_min_bump() {
+        // gas_target = 100, delta = 1: raw bump (100*1/100/8 = 0) rounds to 0, so the max(1) floor kicks in
+        assert_eq!(predict_base_fee(101, 200, U256::from(100u64)), U256::from(101u64));
+    }
+
+    #[test]
+    fn test_predict_base_fee_below_target() {
+        // gas_target = 10, delta = 5, drop = 100 * 5 / 10 / 8 = 6
+        assert_eq!(predict_base_fee(5, 20, U256::from(100u64)), U256::from(94u64));
+    }
+
+    #[test]
+    fn test_predict_base_fee_below_target_floors_at_zero() {
+        assert_eq!(predict_base_fee(0, 20, U256::ZERO), U256::ZERO);
+    }
+
+    #[test]
+    fn test_predict_base_fee_zero_gas_target() {
+        assert_eq!(predict_base_fee(0, 1, U256::from(100u64)), U256::from(100u64));
+    }
+
+    #[test]
+    fn test_forecast_base_fees_is_monotonic_under_sustained_congestion() {
+        let forecast = forecast_base_fees(U256::from(1000u64), 15, 20, 3);
+
+        assert_eq!(forecast, vec![U256::from(1062u64), U256::from(1128u64), U256::from(1198u64)]);
+        assert!(forecast.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_net_profit_eth_saturates_instead_of_underflowing() {
+        let data = TxComposeData {
+            gas: 100,
+            parent_gas_used: 10,
+            parent_gas_limit: 20,
+            parent_base_fee: U256::from(1000u64),
+            ..Default::default()
+        };
+
+        assert_eq!(data.net_profit_eth(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_best_net_profit_swap_tracks_first_request() {
+        let mut best = TxComposeBest::default();
+        let data = TxComposeData { gas: 0, ..Default::default() };
+
+        assert!(best.check(&data));
+        assert!(best.best_net_profit_swap.is_some());
+    }
 }
\ No newline at end of file